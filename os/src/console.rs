@@ -1,4 +1,5 @@
 //! SBI console driver, for text output
+use crate::config::STDERR_COLOR;
 use crate::sbi::console_putchar;
 use core::fmt::{self, Write};
 
@@ -17,6 +18,23 @@ pub fn print(args: fmt::Arguments) {
     Stdout.write_fmt(args).unwrap();
 }
 
+/// Output streams sharing the console
+#[derive(Copy, Clone)]
+pub enum Stream {
+    /// standard output
+    Stdout,
+    /// standard error, tagged in red if `STDERR_COLOR` is set
+    Stderr,
+}
+
+/// Write `s` to the console as part of `stream`.
+pub fn write_stream(stream: Stream, s: &str) {
+    match stream {
+        Stream::Stderr if STDERR_COLOR => print(format_args!("\u{1B}[31m{}\u{1B}[0m", s)),
+        _ => print(format_args!("{}", s)),
+    }
+}
+
 /// Print! to the host console using the format string and arguments.
 #[macro_export]
 macro_rules! print {
@@ -2,25 +2,88 @@
 
 use crate::config::KERNEL_HEAP_SIZE;
 use buddy_system_allocator::LockedHeap;
+use core::alloc::{GlobalAlloc, Layout};
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+/// Buddy system heap that also keeps track of its peak usage and failures
+///
+/// Current usage is read from the allocator itself, which counts the
+/// power-of-two blocks actually handed out rather than the bytes requested.
+struct StatHeap {
+    /// the underlying allocator
+    heap: LockedHeap,
+    /// the largest number of bytes allocated at once
+    peak: AtomicUsize,
+    /// number of allocations that could not be served
+    failures: AtomicUsize,
+}
+
+unsafe impl GlobalAlloc for StatHeap {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = self.heap.alloc(layout);
+        if ptr.is_null() {
+            self.failures.fetch_add(1, Ordering::Relaxed);
+        } else {
+            let used = self.heap.lock().stats_alloc_actual();
+            self.peak.fetch_max(used, Ordering::Relaxed);
+        }
+        ptr
+    }
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        self.heap.dealloc(ptr, layout);
+    }
+}
 
 #[global_allocator]
 /// heap allocator instance
-static HEAP_ALLOCATOR: LockedHeap = LockedHeap::empty();
+static HEAP_ALLOCATOR: StatHeap = StatHeap {
+    heap: LockedHeap::empty(),
+    peak: AtomicUsize::new(0),
+    failures: AtomicUsize::new(0),
+};
 
 /// heap space ([u8; KERNEL_HEAP_SIZE])
 static mut HEAP_SPACE: [u8; KERNEL_HEAP_SIZE] = [0; KERNEL_HEAP_SIZE];
 
+/// Usage statistics of the kernel heap
+#[derive(Clone, Copy, Debug)]
+pub struct HeapStats {
+    /// size of the heap in bytes
+    pub total: usize,
+    /// bytes currently allocated, counting whole buddy blocks
+    pub used: usize,
+    /// the largest number of bytes allocated at once
+    pub peak: usize,
+    /// number of allocations that could not be served
+    pub failures: usize,
+}
+
 /// initiate heap allocator
 pub fn init_heap() {
     unsafe {
         HEAP_ALLOCATOR
+            .heap
             .lock()
             .init(HEAP_SPACE.as_ptr() as usize, KERNEL_HEAP_SIZE);
     }
 }
 
+/// get usage statistics of the kernel heap
+pub fn heap_stats() -> HeapStats {
+    HeapStats {
+        total: KERNEL_HEAP_SIZE,
+        used: HEAP_ALLOCATOR.heap.lock().stats_alloc_actual(),
+        peak: HEAP_ALLOCATOR.peak.load(Ordering::Relaxed),
+        failures: HEAP_ALLOCATOR.failures.load(Ordering::Relaxed),
+    }
+}
+
 #[alloc_error_handler]
 /// panic when heap allocation error occurs
 pub fn handle_alloc_error(layout: core::alloc::Layout) -> ! {
-    panic!("Heap allocation error, layout = {:?}", layout);
+    panic!(
+        "Heap allocation error, layout = {:?}, heap = {:?}",
+        layout,
+        heap_stats()
+    );
 }
@@ -1,8 +1,13 @@
 //! The panic handler
 
+use crate::loader::kernel_stack_bounds;
 use crate::sbi::shutdown;
+use core::arch::asm;
 use core::panic::PanicInfo;
 
+/// max number of frames printed by [`backtrace`]
+const MAX_BACKTRACE_DEPTH: usize = 32;
+
 #[panic_handler]
 /// panic handler
 fn panic(info: &PanicInfo) -> ! {
@@ -16,5 +21,53 @@ fn panic(info: &PanicInfo) -> ! {
     } else {
         println!("[kernel] Panicked: {}", info.message().unwrap());
     }
+    backtrace();
     shutdown()
 }
+
+/// Print the return addresses of the current call stack.
+///
+/// The kernel is built with frame pointers, so each frame saves `ra` at
+/// `fp - 8` and the caller's `fp` at `fp - 16`. The walk stops once `fp`
+/// leaves the kernel stack it started on, or a return address falls outside
+/// the kernel `.text`, e.g. at the user `fp` saved on entry to `trap_handler`.
+fn backtrace() {
+    extern "C" {
+        fn stext();
+        fn etext();
+        fn boot_stack_lower_bound();
+        fn boot_stack_top();
+    }
+    let mut fp: usize;
+    unsafe {
+        asm!("mv {}, fp", out(reg) fp);
+    }
+    let boot_stack = (boot_stack_lower_bound as usize, boot_stack_top as usize);
+    let (bottom, top) = if (boot_stack.0..boot_stack.1).contains(&fp) {
+        boot_stack
+    } else if let Some(bounds) = kernel_stack_bounds(fp) {
+        bounds
+    } else {
+        println!("[kernel] Backtrace unavailable: fp = {:#x} is not on a kernel stack", fp);
+        return;
+    };
+    let text = stext as usize..etext as usize;
+    println!("[kernel] Backtrace:");
+    for depth in 0..MAX_BACKTRACE_DEPTH {
+        if fp < bottom + 16 || fp > top || fp % 8 != 0 {
+            return;
+        }
+        let ra = unsafe { *((fp - 8) as *const usize) };
+        let prev_fp = unsafe { *((fp - 16) as *const usize) };
+        if !text.contains(&ra) {
+            return;
+        }
+        println!("[kernel]   #{} ra = {:#x}", depth, ra);
+        // frames only grow towards the stack top
+        if prev_fp <= fp {
+            return;
+        }
+        fp = prev_fp;
+    }
+    println!("[kernel]   ... (truncated after {} frames)", MAX_BACKTRACE_DEPTH);
+}
@@ -48,11 +48,50 @@ impl UserStack {
     }
 }
 
+/// Get the bounds `(bottom, top)` of the kernel stack containing `addr`, if any.
+pub fn kernel_stack_bounds(addr: usize) -> Option<(usize, usize)> {
+    KERNEL_STACK
+        .iter()
+        .map(|stack| (stack.data.as_ptr() as usize, stack.get_sp()))
+        .find(|&(bottom, top)| (bottom..top).contains(&addr))
+}
+
 /// Get base address of app i.
 fn get_base_i(app_id: usize) -> usize {
     APP_BASE_ADDRESS + app_id * APP_SIZE_LIMIT
 }
 
+/// Check whether `[start, start + len)` lies in memory owned by app `app_id`,
+/// i.e. inside its image region or its user stack.
+pub fn is_user_range(app_id: usize, start: usize, len: usize) -> bool {
+    let end = match start.checked_add(len) {
+        Some(end) => end,
+        None => return false,
+    };
+    let base = get_base_i(app_id);
+    let stack = &USER_STACK[app_id];
+    let stack_bottom = stack.data.as_ptr() as usize;
+    (start >= base && end <= base + APP_SIZE_LIMIT)
+        || (start >= stack_bottom && end <= stack.get_sp())
+}
+
+/// Tell where `addr` lies relative to the memory of app `app_id`.
+///
+/// User stacks are not guarded in ch3, so a stack overflow silently runs into
+/// the stack of the previous app and can't be told apart here.
+pub fn describe_user_addr(app_id: usize, addr: usize) -> &'static str {
+    let base = get_base_i(app_id);
+    let stack = &USER_STACK[app_id];
+    let stack_bottom = stack.data.as_ptr() as usize;
+    if (base..base + APP_SIZE_LIMIT).contains(&addr) {
+        "in the app image"
+    } else if (stack_bottom..stack.get_sp()).contains(&addr) {
+        "in the user stack"
+    } else {
+        "outside the app's memory"
+    }
+}
+
 /// Get the total number of applications.
 pub fn get_num_app() -> usize {
     extern "C" {
@@ -30,6 +30,19 @@ impl Log for SimpleLogger {
     fn flush(&self) {}
 }
 
+/// Log at trace level, prefixed with the current task id and the sequence
+/// number of the syscall it is handling, so that all lines logged by one
+/// syscall can be grouped together.
+#[macro_export]
+macro_rules! ktrace {
+    ($fmt: literal $(, $($arg: tt)+)?) => {
+        if ::log::log_enabled!(::log::Level::Trace) {
+            let (task_id, seq) = $crate::task::current_trace_id();
+            ::log::trace!(concat!("[kernel] task {} #{}: ", $fmt), task_id, seq $(, $($arg)+)?);
+        }
+    }
+}
+
 /// initiate logger
 pub fn init() {
     static LOGGER: SimpleLogger = SimpleLogger;
@@ -33,7 +33,9 @@ pub mod config;
 mod heap_alloc;
 pub mod lang_items;
 mod loader;
+#[macro_use]
 pub mod logging;
+pub mod mm;
 pub mod sbi;
 pub mod sync;
 pub mod syscall;
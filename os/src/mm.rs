@@ -0,0 +1,65 @@
+//! Copying data between the kernel and user memory
+//!
+//! In chapter 3 there is no address translation yet, so a user pointer can be
+//! dereferenced directly. It is only trusted, however, after checking that the
+//! whole object lies in memory owned by the current app (see
+//! [`crate::loader::is_user_range`]), which rejects kernel addresses as well as
+//! memory of other apps. Every syscall taking a user pointer goes through the
+//! helpers here and returns -1 when they fail with [`BadAddress`]. Values are
+//! copied byte by byte, so user pointers don't need to be aligned.
+
+use crate::loader::is_user_range;
+use crate::task::current_task_id;
+use core::mem::size_of;
+
+/// Error of a user pointer not pointing into memory of the current app
+#[derive(Debug)]
+pub struct BadAddress;
+
+/// Types that can be read from user memory by [`copy_from_user`]
+///
+/// # Safety
+///
+/// Every bit pattern must be a valid value of the type, as user memory is not
+/// trusted: plain structs of integers qualify, while enums, `bool` and
+/// references don't.
+pub unsafe trait UserData {}
+
+/// Copy `src` to the user address `dst`.
+///
+/// Fail with [`BadAddress`] if `dst` doesn't point into memory of the current app.
+pub fn copy_to_user<T>(dst: *mut T, src: &T) -> Result<(), BadAddress> {
+    let len = size_of::<T>();
+    if !is_user_range(current_task_id(), dst as usize, len) {
+        return Err(BadAddress);
+    }
+    unsafe {
+        core::ptr::copy_nonoverlapping(src as *const T as *const u8, dst as *mut u8, len);
+    }
+    Ok(())
+}
+
+/// Copy the value at user address `src` into `dst`.
+///
+/// Fail with [`BadAddress`] if `src` doesn't point into memory of the current app.
+pub fn copy_from_user<T: UserData>(src: *const T, dst: &mut T) -> Result<(), BadAddress> {
+    let len = size_of::<T>();
+    if !is_user_range(current_task_id(), src as usize, len) {
+        return Err(BadAddress);
+    }
+    // SAFETY: any bytes make a valid `T`, as `T: UserData`
+    unsafe {
+        core::ptr::copy_nonoverlapping(src as *const u8, dst as *mut T as *mut u8, len);
+    }
+    Ok(())
+}
+
+/// Borrow the `len` bytes at user address `addr` as a slice.
+///
+/// Fail with [`BadAddress`] if the buffer doesn't lie entirely in memory of the current app.
+pub fn user_buffer(addr: usize, len: usize) -> Result<&'static [u8], BadAddress> {
+    if !is_user_range(current_task_id(), addr, len) {
+        return Err(BadAddress);
+    }
+    Ok(unsafe { core::slice::from_raw_parts(addr as *const u8, len) })
+}
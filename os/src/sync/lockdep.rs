@@ -0,0 +1,128 @@
+//! Borrow order checking for `UPSafeCell`
+//!
+//! Only built in debug builds. Every borrow of a tracked cell is pushed on a
+//! stack of held borrows, and a borrow taken while others are held adds
+//! "held before new" edges to a global acquired-before graph. Borrowing a cell
+//! which is already held, or in an order that closes a cycle in the graph,
+//! panics at once naming the call sites involved, instead of failing later
+//! with an opaque `BorrowMutError` or a deadlock on real locks.
+
+use core::cell::RefCell;
+use core::panic::Location;
+
+/// max number of borrows held at once
+const MAX_HELD: usize = 16;
+/// max number of edges in the acquired-before graph
+const MAX_EDGES: usize = 64;
+
+/// Call site of a borrow
+type Site = &'static Location<'static>;
+
+/// Cell `to` was borrowed at `site` while cell `from` was held
+#[derive(Copy, Clone)]
+struct Edge {
+    from: usize,
+    to: usize,
+    site: Site,
+}
+
+/// Held borrows and the acquired-before graph
+struct LockDep {
+    /// held borrows as (cell id, call site), in borrow order
+    held: [Option<(usize, Site)>; MAX_HELD],
+    held_len: usize,
+    /// edges of the acquired-before graph, recorded until it is full
+    edges: [Option<Edge>; MAX_EDGES],
+    edges_len: usize,
+}
+
+/// Lockdep state, kept in a plain `RefCell` as it can't check itself
+struct LockDepCell(RefCell<LockDep>);
+
+unsafe impl Sync for LockDepCell {}
+
+static LOCKDEP: LockDepCell = LockDepCell(RefCell::new(LockDep {
+    held: [None; MAX_HELD],
+    held_len: 0,
+    edges: [None; MAX_EDGES],
+    edges_len: 0,
+}));
+
+impl LockDep {
+    fn held(&self) -> impl Iterator<Item = (usize, Site)> + '_ {
+        self.held[..self.held_len].iter().map(|held| held.unwrap())
+    }
+
+    fn edges(&self) -> impl Iterator<Item = Edge> + '_ {
+        self.edges[..self.edges_len].iter().map(|edge| edge.unwrap())
+    }
+
+    /// If `to` can be reached from `from`, get the site of the first edge on the way.
+    fn find_path(&self, from: usize, to: usize) -> Option<Site> {
+        // every queued node but `from` is the target of a distinct edge
+        let mut queue: [(usize, Option<Site>); MAX_EDGES + 1] = [(from, None); MAX_EDGES + 1];
+        let (mut head, mut tail) = (0, 1);
+        while head < tail {
+            let (node, first) = queue[head];
+            head += 1;
+            for edge in self.edges().filter(|edge| edge.from == node) {
+                let first = first.or(Some(edge.site));
+                if edge.to == to {
+                    return first;
+                }
+                if !queue[..tail].iter().any(|(queued, _)| *queued == edge.to) {
+                    queue[tail] = (edge.to, first);
+                    tail += 1;
+                }
+            }
+        }
+        None
+    }
+
+    fn add_edge(&mut self, from: usize, to: usize, site: Site) {
+        let known = self.edges().any(|edge| edge.from == from && edge.to == to);
+        if !known && self.edges_len < MAX_EDGES {
+            self.edges[self.edges_len] = Some(Edge { from, to, site });
+            self.edges_len += 1;
+        }
+    }
+}
+
+/// Check and record a borrow of cell `id` at `site`.
+pub fn acquire(id: usize, site: Site) {
+    let mut dep = LOCKDEP.0.borrow_mut();
+    if let Some((_, holder)) = dep.held().find(|(held, _)| *held == id) {
+        panic!(
+            "[lockdep] cell borrowed at {} is already borrowed at {}",
+            site, holder
+        );
+    }
+    for i in 0..dep.held_len {
+        let (held, held_site) = dep.held[i].unwrap();
+        if let Some(reverse_site) = dep.find_path(id, held) {
+            panic!(
+                "[lockdep] cell borrowed at {} while holding the one borrowed at {}, \
+                 but the reverse order was seen at {}",
+                site, held_site, reverse_site
+            );
+        }
+        dep.add_edge(held, id, site);
+    }
+    if dep.held_len == MAX_HELD {
+        panic!("[lockdep] too many borrows held at {}", site);
+    }
+    let len = dep.held_len;
+    dep.held[len] = Some((id, site));
+    dep.held_len += 1;
+}
+
+/// Record the end of the latest borrow of cell `id`.
+pub fn release(id: usize) {
+    let mut dep = LOCKDEP.0.borrow_mut();
+    let len = dep.held_len;
+    if let Some(pos) = dep.held[..len].iter().rposition(|held| held.unwrap().0 == id) {
+        dep.held.copy_within(pos + 1..len, pos);
+        dep.held[len - 1] = None;
+        dep.held_len -= 1;
+    }
+}
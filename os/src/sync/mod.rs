@@ -1,5 +1,7 @@
 //! Synchronization and interior mutability primitives
 
+#[cfg(debug_assertions)]
+mod lockdep;
 mod up;
 
-pub use up::UPSafeCell;
+pub use up::{UPRefMut, UPSafeCell};
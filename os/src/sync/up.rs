@@ -1,5 +1,8 @@
 //! Uniprocessor interior mutability primitives
 use core::cell::{RefCell, RefMut};
+use core::ops::{Deref, DerefMut};
+#[cfg(debug_assertions)]
+use core::panic::Location;
 
 /// Wrap a static data structure inside it so that we are
 /// able to access it without any `unsafe`.
@@ -8,9 +11,16 @@ use core::cell::{RefCell, RefMut};
 ///
 /// In order to get mutable reference of inner data, call
 /// `exclusive_access`.
+///
+/// In debug builds, borrows are checked by lockdep (see `sync::lockdep`),
+/// which identifies a cell by its address: a cell must not be moved once it
+/// has been accessed.
 pub struct UPSafeCell<T> {
     /// inner data
     inner: RefCell<T>,
+    /// whether borrows are checked by lockdep
+    #[cfg_attr(not(debug_assertions), allow(dead_code))]
+    tracked: bool,
 }
 
 unsafe impl<T> Sync for UPSafeCell<T> {}
@@ -21,10 +31,63 @@ impl<T> UPSafeCell<T> {
     pub unsafe fn new(value: T) -> Self {
         Self {
             inner: RefCell::new(value),
+            tracked: true,
+        }
+    }
+    /// Same as [`UPSafeCell::new`], but borrows of the cell are not checked
+    /// by lockdep. Only use it for cells known to be borrowed safely.
+    pub unsafe fn new_untracked(value: T) -> Self {
+        Self {
+            inner: RefCell::new(value),
+            tracked: false,
         }
     }
     /// Panic if the data has been borrowed.
-    pub fn exclusive_access(&self) -> RefMut<'_, T> {
-        self.inner.borrow_mut()
+    #[track_caller]
+    pub fn exclusive_access(&self) -> UPRefMut<'_, T> {
+        #[cfg(debug_assertions)]
+        let id = if self.tracked {
+            let id = self as *const Self as usize;
+            super::lockdep::acquire(id, Location::caller());
+            Some(id)
+        } else {
+            None
+        };
+        UPRefMut {
+            inner: self.inner.borrow_mut(),
+            #[cfg(debug_assertions)]
+            id,
+        }
+    }
+}
+
+/// Mutable reference to the data of an [`UPSafeCell`]
+pub struct UPRefMut<'a, T> {
+    /// the borrow of the inner data
+    inner: RefMut<'a, T>,
+    /// id of the cell if it is tracked by lockdep
+    #[cfg(debug_assertions)]
+    id: Option<usize>,
+}
+
+impl<T> Deref for UPRefMut<'_, T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        &self.inner
+    }
+}
+
+impl<T> DerefMut for UPRefMut<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+}
+
+impl<T> Drop for UPRefMut<'_, T> {
+    fn drop(&mut self) {
+        #[cfg(debug_assertions)]
+        if let Some(id) = self.id {
+            super::lockdep::release(id);
+        }
     }
 }
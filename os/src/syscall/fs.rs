@@ -1,19 +1,125 @@
 //! File and filesystem-related syscalls
 
+use crate::console::{write_stream, Stream};
+use crate::mm::{copy_from_user, user_buffer, UserData};
+use alloc::vec::Vec;
+
 const FD_STDOUT: usize = 1;
+const FD_STDERR: usize = 2;
+/// max number of buffers in one `sys_writev`
+const IOV_MAX: usize = 1024;
+
+/// A user buffer given by its start address and length
+#[repr(C)]
+pub struct IoVec {
+    /// start address of the buffer
+    pub base: usize,
+    /// length of the buffer
+    pub len: usize,
+}
+
+// SAFETY: `IoVec` only holds integers
+unsafe impl UserData for IoVec {}
+
+/// get the console stream written through `fd`, if any
+fn console_stream(fd: usize) -> Option<Stream> {
+    match fd {
+        FD_STDOUT => Some(Stream::Stdout),
+        FD_STDERR => Some(Stream::Stderr),
+        _ => None,
+    }
+}
 
 /// write buf of length `len`  to a file with `fd`
 pub fn sys_write(fd: usize, buf: *const u8, len: usize) -> isize {
-    trace!("kernel: sys_write");
-    match fd {
-        FD_STDOUT => {
-            let slice = unsafe { core::slice::from_raw_parts(buf, len) };
-            let str = core::str::from_utf8(slice).unwrap();
-            print!("{}", str);
+    ktrace!("sys_write");
+    match console_stream(fd) {
+        // like empty iovecs in `sys_writev`, accepted whatever `buf` is
+        Some(_) if len == 0 => 0,
+        Some(stream) => {
+            let slice = match user_buffer(buf as usize, len) {
+                Ok(slice) => slice,
+                Err(_) => return -1,
+            };
+            let str = match core::str::from_utf8(slice) {
+                Ok(str) => str,
+                Err(_) => return -1,
+            };
+            write_stream(stream, str);
             len as isize
         }
-        _ => {
-            panic!("Unsupported fd in sys_write!");
+        None => -1,
+    }
+}
+
+/// Decode `slices` as one UTF-8 stream, passing it to `f` piece by piece.
+///
+/// A character may be split across slices. Return `false` on invalid UTF-8,
+/// in which case `f` may already have been called for the valid prefix.
+fn for_each_utf8_piece(slices: &[&[u8]], mut f: impl FnMut(&str)) -> bool {
+    // bytes of a character started at the end of the previous slice
+    let mut carry = [0u8; 4];
+    let mut carry_len = 0;
+    for &slice in slices {
+        let mut rest = slice;
+        while carry_len > 0 && !rest.is_empty() {
+            carry[carry_len] = rest[0];
+            carry_len += 1;
+            rest = &rest[1..];
+            match core::str::from_utf8(&carry[..carry_len]) {
+                Ok(s) => {
+                    f(s);
+                    carry_len = 0;
+                }
+                Err(e) if e.error_len().is_some() => return false,
+                Err(_) => {}
+            }
+        }
+        match core::str::from_utf8(rest) {
+            Ok(s) => f(s),
+            // the slice ends in the middle of a character
+            Err(e) if e.error_len().is_none() => {
+                let (valid, tail) = rest.split_at(e.valid_up_to());
+                // SAFETY: `from_utf8` checked the bytes up to `valid_up_to`
+                f(unsafe { core::str::from_utf8_unchecked(valid) });
+                carry[..tail.len()].copy_from_slice(tail);
+                carry_len = tail.len();
+            }
+            Err(_) => return false,
+        }
+    }
+    carry_len == 0
+}
+
+/// write the `iovcnt` buffers described by `iov` to a file with `fd`, in order
+pub fn sys_writev(fd: usize, iov: *const IoVec, iovcnt: usize) -> isize {
+    ktrace!("sys_writev");
+    let stream = match console_stream(fd) {
+        Some(stream) if iovcnt <= IOV_MAX => stream,
+        _ => return -1,
+    };
+    // check every buffer before writing any, so that nothing is written on error
+    let mut slices = Vec::with_capacity(iovcnt);
+    let mut total = 0;
+    for i in 0..iovcnt {
+        let mut vec = IoVec { base: 0, len: 0 };
+        if copy_from_user(iov.wrapping_add(i), &mut vec).is_err() {
+            return -1;
+        }
+        // empty buffers are skipped, whatever their base address
+        if vec.len == 0 {
+            continue;
         }
+        match user_buffer(vec.base, vec.len) {
+            Ok(slice) => slices.push(slice),
+            Err(_) => return -1,
+        }
+        total += vec.len;
+    }
+    // the buffers are written as one stream, so they must be valid UTF-8 as a whole
+    if !for_each_utf8_piece(&slices, |_| {}) {
+        return -1;
     }
+    for_each_utf8_piece(&slices, |s| write_stream(stream, s));
+    total as isize
 }
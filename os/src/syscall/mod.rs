@@ -12,14 +12,35 @@
 
 /// write syscall
 const SYSCALL_WRITE: usize = 64;
+/// writev syscall
+const SYSCALL_WRITEV: usize = 66;
 /// exit syscall
 const SYSCALL_EXIT: usize = 93;
+/// clock_gettime syscall
+const SYSCALL_CLOCK_GETTIME: usize = 113;
+/// clock_getres syscall
+const SYSCALL_CLOCK_GETRES: usize = 114;
+/// clock_nanosleep syscall
+const SYSCALL_CLOCK_NANOSLEEP: usize = 115;
 /// yield syscall
 const SYSCALL_YIELD: usize = 124;
 /// gettime syscall
 const SYSCALL_GET_TIME: usize = 169;
 /// taskinfo syscall
 const SYSCALL_TASK_INFO: usize = 410;
+/// meminfo syscall
+const SYSCALL_MEM_INFO: usize = 411;
+/// schedstats syscall
+const SYSCALL_SCHED_STATS: usize = 412;
+/// sysinfo syscall
+const SYSCALL_SYSINFO: usize = 413;
+/// setitimer syscall
+const SYSCALL_SETITIMER: usize = 414;
+/// timer_getoverrun syscall
+const SYSCALL_TIMER_GETOVERRUN: usize = 415;
+/// debug syscall panicking the kernel, only in debug builds
+#[cfg(debug_assertions)]
+const SYSCALL_DEBUG_PANIC: usize = 416;
 
 mod fs;
 mod process;
@@ -27,13 +48,32 @@ mod process;
 use fs::*;
 use process::*;
 /// handle syscall exception with `syscall_id` and other arguments
-pub fn syscall(syscall_id: usize, args: [usize; 3]) -> isize {
+pub fn syscall(syscall_id: usize, args: [usize; 4]) -> isize {
     match syscall_id {
         SYSCALL_WRITE => sys_write(args[0], args[1] as *const u8, args[2]),
+        SYSCALL_WRITEV => sys_writev(args[0], args[1] as *const IoVec, args[2]),
         SYSCALL_EXIT => sys_exit(args[0] as i32),
+        SYSCALL_CLOCK_GETTIME => sys_clock_gettime(args[0], args[1] as *mut TimeSpec),
+        SYSCALL_CLOCK_GETRES => sys_clock_getres(args[0], args[1] as *mut TimeSpec),
+        SYSCALL_CLOCK_NANOSLEEP => sys_clock_nanosleep(
+            args[0],
+            args[1],
+            args[2] as *const TimeSpec,
+            args[3] as *mut TimeSpec,
+        ),
         SYSCALL_YIELD => sys_yield(),
         SYSCALL_GET_TIME => sys_get_time(args[0] as *mut TimeVal, args[1]),
         SYSCALL_TASK_INFO => sys_task_info(args[0] as *mut TaskInfo),
-        _ => panic!("Unsupported syscall_id: {}", syscall_id),
+        SYSCALL_MEM_INFO => sys_mem_info(args[0] as *mut MemInfo),
+        SYSCALL_SCHED_STATS => sys_sched_stats(args[0] as *mut SchedInfo),
+        SYSCALL_SYSINFO => sys_sysinfo(args[0] as *mut SysInfo),
+        SYSCALL_SETITIMER => sys_setitimer(args[0], args[1]),
+        SYSCALL_TIMER_GETOVERRUN => sys_timer_getoverrun(),
+        #[cfg(debug_assertions)]
+        SYSCALL_DEBUG_PANIC => sys_debug_panic(),
+        _ => {
+            ktrace!("unsupported syscall_id {}", syscall_id);
+            -1
+        }
     }
 }
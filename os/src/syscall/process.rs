@@ -1,11 +1,15 @@
 //! Process management syscalls
 use crate::{
     config::MAX_SYSCALL_NUM,
-    task::{exit_current_and_run_next, suspend_current_and_run_next, TaskStatus},
-    timer::get_time_us,
+    heap_alloc::heap_stats,
+    mm::{copy_from_user, copy_to_user, UserData},
+    task::{
+        exit_current_and_run_next, sched_stats, suspend_current_and_run_next, TaskStatus, FSHIFT,
+    },
+    timer::{get_time_ns, get_time_res_ns, get_time_us},
 };
 use crate::task::TASK_MANAGER;
-use crate::timer::{get_time_ms};
+use crate::timer::{get_time_ms, IntervalTimer};
 
 #[repr(C)]
 #[derive(Debug)]
@@ -14,6 +18,42 @@ pub struct TimeVal {
     pub usec: usize,
 }
 
+/// Time with nanosecond precision
+#[repr(C)]
+#[derive(Debug)]
+pub struct TimeSpec {
+    /// Seconds
+    pub sec: usize,
+    /// Nanoseconds
+    pub nsec: usize,
+}
+
+// SAFETY: `TimeSpec` only holds integers
+unsafe impl UserData for TimeSpec {}
+
+impl TimeSpec {
+    /// Split `ns` nanoseconds into seconds and nanoseconds.
+    fn from_ns(ns: usize) -> Self {
+        Self {
+            sec: ns / 1_000_000_000,
+            nsec: ns % 1_000_000_000,
+        }
+    }
+    /// Get the time in nanoseconds, saturating on overflow.
+    fn as_ns(&self) -> usize {
+        self.sec
+            .saturating_mul(1_000_000_000)
+            .saturating_add(self.nsec)
+    }
+}
+
+/// Wall-clock time; without an RTC it starts at boot like `CLOCK_MONOTONIC`
+const CLOCK_REALTIME: usize = 0;
+/// Time since boot
+const CLOCK_MONOTONIC: usize = 1;
+/// `sys_clock_nanosleep` flag: the request is an absolute time, not a duration
+const TIMER_ABSTIME: usize = 1;
+
 /// Task information
 #[allow(dead_code)]
 pub struct TaskInfo {
@@ -25,44 +65,246 @@ pub struct TaskInfo {
     time: usize,
 }
 
+/// Kernel memory information
+#[repr(C)]
+pub struct MemInfo {
+    /// Size of the kernel heap in bytes
+    heap_total: usize,
+    /// Bytes currently allocated from the kernel heap
+    heap_used: usize,
+    /// Peak number of bytes allocated from the kernel heap
+    heap_peak: usize,
+    /// Number of failed kernel heap allocations
+    heap_failures: usize,
+}
+
+/// Scheduling statistics of the calling task and of the scheduler
+#[repr(C)]
+pub struct SchedInfo {
+    /// Times the task gave up the CPU by itself
+    voluntary_switches: usize,
+    /// Times the task was preempted by a timer interrupt
+    involuntary_switches: usize,
+    /// Times the task was picked to run
+    dispatches: usize,
+    /// Total time in ms the task spent ready but not running
+    ready_time: usize,
+    /// Total number of dispatches of all tasks
+    total_dispatches: usize,
+    /// Current number of ready tasks
+    ready_len: usize,
+    /// Largest number of ready tasks seen at once
+    max_ready_len: usize,
+}
+
+/// System load information
+///
+/// Loads and CPU usage are fixed-point numbers with `FSHIFT` fractional bits.
+#[repr(C)]
+pub struct SysInfo {
+    /// Time in ms since boot
+    uptime: usize,
+    /// Load averages over 1, 5 and 15 seconds
+    loads: [usize; 3],
+    /// Recent CPU usage of the calling task, where 1.0 means all of the CPU
+    recent_cpu: usize,
+    /// Number of fractional bits in `loads` and `recent_cpu`
+    fshift: usize,
+}
+
 /// task exits and submit an exit code
 pub fn sys_exit(exit_code: i32) -> ! {
-    trace!("[kernel] Application exited with code {}", exit_code);
-    exit_current_and_run_next();
+    ktrace!("sys_exit: application exited with code {}", exit_code);
+    exit_current_and_run_next(exit_code);
     panic!("Unreachable in sys_exit!");
 }
 
 /// current task gives up resources for other tasks
 pub fn sys_yield() -> isize {
-    trace!("kernel: sys_yield");
+    ktrace!("sys_yield");
     suspend_current_and_run_next();
     0
 }
 
 /// get time with second and microsecond
 pub fn sys_get_time(ts: *mut TimeVal, _tz: usize) -> isize {
-    trace!("kernel: sys_get_time");
+    ktrace!("sys_get_time");
     let us = get_time_us();
-    unsafe {
-        *ts = TimeVal {
-            sec: us / 1_000_000,
-            usec: us % 1_000_000,
-        };
+    let time_val = TimeVal {
+        sec: us / 1_000_000,
+        usec: us % 1_000_000,
+    };
+    match copy_to_user(ts, &time_val) {
+        Ok(()) => 0,
+        Err(_) => -1,
+    }
+}
+
+/// get the time of clock `clock_id` with nanosecond resolution
+pub fn sys_clock_gettime(clock_id: usize, ts: *mut TimeSpec) -> isize {
+    ktrace!("sys_clock_gettime");
+    if clock_id != CLOCK_REALTIME && clock_id != CLOCK_MONOTONIC {
+        return -1;
+    }
+    match copy_to_user(ts, &TimeSpec::from_ns(get_time_ns())) {
+        Ok(()) => 0,
+        Err(_) => -1,
+    }
+}
+
+/// get the resolution of clock `clock_id`
+pub fn sys_clock_getres(clock_id: usize, res: *mut TimeSpec) -> isize {
+    ktrace!("sys_clock_getres");
+    if clock_id != CLOCK_REALTIME && clock_id != CLOCK_MONOTONIC {
+        return -1;
+    }
+    match copy_to_user(res, &TimeSpec::from_ns(get_time_res_ns())) {
+        Ok(()) => 0,
+        Err(_) => -1,
+    }
+}
+
+/// sleep until clock `clock_id` reaches the time in `req` if `flags` has
+/// `TIMER_ABSTIME`, otherwise for the duration in `req`.
+///
+/// Tasks can't block in this chapter, so the current task yields until the
+/// deadline passes. Sleeps are never interrupted, so `rem` is left untouched.
+pub fn sys_clock_nanosleep(
+    clock_id: usize,
+    flags: usize,
+    req: *const TimeSpec,
+    _rem: *mut TimeSpec,
+) -> isize {
+    ktrace!("sys_clock_nanosleep");
+    if clock_id != CLOCK_REALTIME && clock_id != CLOCK_MONOTONIC {
+        return -1;
+    }
+    let mut time = TimeSpec { sec: 0, nsec: 0 };
+    if copy_from_user(req, &mut time).is_err() || time.nsec >= 1_000_000_000 {
+        return -1;
+    }
+    let deadline = if flags & TIMER_ABSTIME != 0 {
+        time.as_ns()
+    } else {
+        get_time_ns().saturating_add(time.as_ns())
+    };
+    while get_time_ns() < deadline {
+        suspend_current_and_run_next();
     }
     0
 }
 
 /// YOUR JOB: Finish sys_task_info to pass testcases
 pub fn sys_task_info(ti: *mut TaskInfo) -> isize {
-    trace!("kernel: sys_task_info");
+    ktrace!("sys_task_info");
     let tcb = TASK_MANAGER.get_inner().get_current_task();
     let ms = get_time_ms();
-    unsafe {
-        *ti = TaskInfo {
-            status: TaskStatus::Running,
-            syscall_times: tcb.syscall_times,
-            time: ms - tcb.start_time,
-        }
+    let task_info = TaskInfo {
+        status: TaskStatus::Running,
+        syscall_times: tcb.syscall_times,
+        time: ms - tcb.start_time,
+    };
+    match copy_to_user(ti, &task_info) {
+        Ok(()) => 0,
+        Err(_) => -1,
+    }
+}
+
+/// get kernel heap usage
+pub fn sys_mem_info(mi: *mut MemInfo) -> isize {
+    ktrace!("sys_mem_info");
+    let stats = heap_stats();
+    let mem_info = MemInfo {
+        heap_total: stats.total,
+        heap_used: stats.used,
+        heap_peak: stats.peak,
+        heap_failures: stats.failures,
+    };
+    match copy_to_user(mi, &mem_info) {
+        Ok(()) => 0,
+        Err(_) => -1,
+    }
+}
+
+/// get scheduling statistics of the current task and of the scheduler
+pub fn sys_sched_stats(si: *mut SchedInfo) -> isize {
+    ktrace!("sys_sched_stats");
+    let (task, sched) = sched_stats();
+    let sched_info = SchedInfo {
+        voluntary_switches: task.voluntary_switches,
+        involuntary_switches: task.involuntary_switches,
+        dispatches: task.dispatches,
+        ready_time: task.ready_time,
+        total_dispatches: sched.dispatches,
+        ready_len: sched.ready_len,
+        max_ready_len: sched.max_ready_len,
+    };
+    match copy_to_user(si, &sched_info) {
+        Ok(()) => 0,
+        Err(_) => -1,
     }
+}
+
+/// get the system load and the recent CPU usage of the current task
+pub fn sys_sysinfo(si: *mut SysInfo) -> isize {
+    ktrace!("sys_sysinfo");
+    let (task, sched) = sched_stats();
+    let sys_info = SysInfo {
+        uptime: get_time_ms(),
+        loads: sched.load_avg,
+        recent_cpu: task.recent_cpu,
+        fshift: FSHIFT,
+    };
+    match copy_to_user(si, &sys_info) {
+        Ok(()) => 0,
+        Err(_) => -1,
+    }
+}
+
+/// arm the periodic timer of the current task to expire every `interval_ms`,
+/// the first time after `initial_ms` (or `interval_ms` if it is 0).
+/// An `interval_ms` of 0 disarms the timer. Return -1 if the first
+/// expiration is too far away to be represented.
+pub fn sys_setitimer(interval_ms: usize, initial_ms: usize) -> isize {
+    ktrace!("sys_setitimer");
+    let mut inner = TASK_MANAGER.get_inner();
+    let mut tcb = inner.get_current_task();
+    if interval_ms == 0 {
+        tcb.itimer = IntervalTimer::default();
+        inner.set_current_task(tcb);
+        return 0;
+    }
+    let first = if initial_ms == 0 { interval_ms } else { initial_ms };
+    let next_expire = match get_time_ms().checked_add(first) {
+        Some(next_expire) => next_expire,
+        None => return -1,
+    };
+    tcb.itimer = IntervalTimer {
+        interval: interval_ms,
+        next_expire,
+        expirations: 0,
+    };
+    inner.set_current_task(tcb);
     0
 }
+
+/// get the number of expirations of the periodic timer of the current task
+/// since it was armed, or -1 if it is disarmed
+pub fn sys_timer_getoverrun() -> isize {
+    ktrace!("sys_timer_getoverrun");
+    let mut inner = TASK_MANAGER.get_inner();
+    let mut tcb = inner.get_current_task();
+    if tcb.itimer.interval == 0 {
+        return -1;
+    }
+    tcb.itimer.update(get_time_ms());
+    inner.set_current_task(tcb);
+    tcb.itimer.expirations as isize
+}
+
+/// panic the kernel on purpose, to check the backtrace printed by the panic handler
+#[cfg(debug_assertions)]
+pub fn sys_debug_panic() -> isize {
+    ktrace!("sys_debug_panic");
+    panic!("sys_debug_panic called");
+}
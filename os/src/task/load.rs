@@ -0,0 +1,22 @@
+//! Load average and recent CPU usage estimation
+//!
+//! Both are exponentially decayed averages in fixed-point, where [`FIXED_1`]
+//! stands for 1.0, sampled once every [`LOAD_PERIOD_TICKS`] timer ticks. The
+//! load average counts `Ready` and `Running` tasks, while the recent CPU usage
+//! of a task is the fraction of the ticks it was running.
+
+use crate::timer::TICKS_PER_SEC;
+
+/// Number of fractional bits
+pub const FSHIFT: usize = 11;
+/// 1.0 in fixed-point
+pub const FIXED_1: usize = 1 << FSHIFT;
+/// Ticks between two samples, i.e. one second
+pub const LOAD_PERIOD_TICKS: usize = TICKS_PER_SEC;
+/// Decay factors `FIXED_1 * exp(-1 / n)` of averages over n = 1, 5 and 15 samples
+pub const EXP: [usize; 3] = [753, 1677, 1916];
+
+/// Decay `avg` by `exp` and add the new sample `value`.
+pub fn calc_load(avg: usize, exp: usize, value: usize) -> usize {
+    (avg * exp + value * (FIXED_1 - exp)) / FIXED_1
+}
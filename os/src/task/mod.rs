@@ -9,20 +9,22 @@
 //! Be careful when you see `__switch` ASM function in `switch.S`. Control flow around this function
 //! might not be what you expect.
 
-use core::cell::RefMut;
-
 use lazy_static::*;
 
 pub use context::TaskContext;
+pub use load::{FIXED_1, FSHIFT};
+use load::{calc_load, EXP, LOAD_PERIOD_TICKS};
 use switch::__switch;
-pub use task::{TaskControlBlock, TaskStatus};
+pub use task::{TaskControlBlock, TaskSchedStats, TaskStatus, UserFault};
 
 use crate::config::{MAX_APP_NUM, MAX_SYSCALL_NUM};
 use crate::loader::{get_num_app, init_app_cx};
-use crate::sync::UPSafeCell;
-use crate::timer::get_time_ms;
+use crate::sbi::shutdown;
+use crate::sync::{UPRefMut, UPSafeCell};
+use crate::timer::{get_time_ms, IntervalTimer};
 
 mod context;
+mod load;
 mod switch;
 #[allow(clippy::module_inception)]
 mod task;
@@ -49,6 +51,29 @@ pub struct TaskManagerInner {
     tasks: [TaskControlBlock; MAX_APP_NUM],
     /// id of current `Running` task
     current_task: usize,
+    /// total number of dispatches
+    dispatches: usize,
+    /// current number of `Ready` tasks
+    ready_len: usize,
+    /// the largest number of `Ready` tasks seen at once while a task is running
+    max_ready_len: usize,
+    /// timer ticks since the first task started
+    ticks: usize,
+    /// load averages over 1, 5 and 15 seconds, in fixed-point
+    load_avg: [usize; 3],
+}
+
+/// Scheduler-wide statistics
+#[derive(Copy, Clone)]
+pub struct SchedStats {
+    /// total number of dispatches
+    pub dispatches: usize,
+    /// current number of `Ready` tasks
+    pub ready_len: usize,
+    /// the largest number of `Ready` tasks seen at once while a task is running
+    pub max_ready_len: usize,
+    /// load averages over 1, 5 and 15 seconds, in fixed-point
+    pub load_avg: [usize; 3],
 }
 
 impl TaskManagerInner {
@@ -61,6 +86,17 @@ impl TaskManagerInner {
     pub fn set_current_task(&mut self, tcb: TaskControlBlock) {
         self.tasks[self.current_task] = tcb
     }
+
+    /// Record that task `id` is picked to run at `now` (in ms), leaving the
+    /// `Ready` tasks.
+    fn record_dispatch(&mut self, id: usize, now: usize) {
+        let stats = &mut self.tasks[id].sched_stats;
+        stats.dispatches += 1;
+        stats.ready_time += now - stats.ready_since;
+        self.dispatches += 1;
+        self.ready_len -= 1;
+        self.max_ready_len = self.max_ready_len.max(self.ready_len);
+    }
 }
 
 lazy_static! {
@@ -72,6 +108,11 @@ lazy_static! {
             task_status: TaskStatus::UnInit,
             syscall_times: [0; MAX_SYSCALL_NUM],
             start_time: 0,
+            syscall_seq: 0,
+            sched_stats: TaskSchedStats::default(),
+            itimer: IntervalTimer::default(),
+            exit_code: 0,
+            last_fault: None,
         }; MAX_APP_NUM];
         for (i, task) in tasks.iter_mut().enumerate() {
             task.task_cx = TaskContext::goto_restore(init_app_cx(i));
@@ -83,6 +124,11 @@ lazy_static! {
                 UPSafeCell::new(TaskManagerInner {
                     tasks,
                     current_task: 0,
+                    dispatches: 0,
+                    ready_len: num_app,
+                    max_ready_len: 0,
+                    ticks: 0,
+                    load_avg: [0; 3],
                 })
             },
         }
@@ -96,9 +142,11 @@ impl TaskManager {
     /// But in ch3, we load apps statically, so the first task is a real app.
     fn run_first_task(&self) -> ! {
         let mut inner = self.inner.exclusive_access();
+        let now = get_time_ms();
+        inner.record_dispatch(0, now);
         let task0 = &mut inner.tasks[0];
         if task0.start_time  == 0 {
-            task0.start_time = get_time_ms();
+            task0.start_time = now;
         }
         task0.task_status = TaskStatus::Running;
         let next_task_cx_ptr = &task0.task_cx as *const TaskContext;
@@ -112,22 +160,79 @@ impl TaskManager {
     }
 
     /// get inner
-    pub fn get_inner(&self) -> RefMut<TaskManagerInner> {
+    #[track_caller]
+    pub fn get_inner(&self) -> UPRefMut<TaskManagerInner> {
         self.inner.exclusive_access()
     }
 
     /// Change the status of current `Running` task into `Ready`.
-    fn mark_current_suspended(&self) {
+    ///
+    /// `preempted` tells whether the task is forced off the CPU rather than yielding.
+    fn mark_current_suspended(&self, preempted: bool) {
+        let mut inner = self.inner.exclusive_access();
+        let current = inner.current_task;
+        let task = &mut inner.tasks[current];
+        task.task_status = TaskStatus::Ready;
+        task.sched_stats.ready_since = get_time_ms();
+        if preempted {
+            task.sched_stats.involuntary_switches += 1;
+        } else {
+            task.sched_stats.voluntary_switches += 1;
+        }
+        inner.ready_len += 1;
+    }
+
+    /// Account a timer tick to the current task, and once per load period
+    /// update the load average and the recent CPU usage of every task.
+    fn on_timer_tick(&self) {
         let mut inner = self.inner.exclusive_access();
         let current = inner.current_task;
-        inner.tasks[current].task_status = TaskStatus::Ready;
+        inner.tasks[current].sched_stats.period_ticks += 1;
+        inner.ticks += 1;
+        if inner.ticks % LOAD_PERIOD_TICKS != 0 {
+            return;
+        }
+        // the interrupted task is still `Running`, so count it as well
+        let active = (inner.ready_len + 1) * FIXED_1;
+        for (avg, exp) in inner.load_avg.iter_mut().zip(EXP) {
+            *avg = calc_load(*avg, exp, active);
+        }
+        for task in inner.tasks[..self.num_app]
+            .iter_mut()
+            .filter(|task| task.task_status != TaskStatus::Exited)
+        {
+            let stats = &mut task.sched_stats;
+            let usage = stats.period_ticks * FIXED_1 / LOAD_PERIOD_TICKS;
+            stats.recent_cpu = calc_load(stats.recent_cpu, EXP[1], usage);
+            stats.period_ticks = 0;
+        }
+    }
+
+    /// Get the scheduling statistics of the current task and of the scheduler.
+    fn sched_stats(&self) -> (TaskSchedStats, SchedStats) {
+        let inner = self.inner.exclusive_access();
+        let stats = SchedStats {
+            dispatches: inner.dispatches,
+            ready_len: inner.ready_len,
+            max_ready_len: inner.max_ready_len,
+            load_avg: inner.load_avg,
+        };
+        (inner.tasks[inner.current_task].sched_stats, stats)
     }
 
     /// Change the status of current `Running` task into `Exited`.
-    fn mark_current_exited(&self) {
+    fn mark_current_exited(&self, exit_code: i32) {
         let mut inner = self.inner.exclusive_access();
         let current = inner.current_task;
         inner.tasks[current].task_status = TaskStatus::Exited;
+        inner.tasks[current].exit_code = exit_code;
+    }
+
+    /// Record the exception raised by the current task.
+    fn record_current_fault(&self, fault: UserFault) {
+        let mut inner = self.inner.exclusive_access();
+        let current = inner.current_task;
+        inner.tasks[current].last_fault = Some(fault);
     }
 
     /// Find next task to run and return task id.
@@ -147,8 +252,10 @@ impl TaskManager {
         if let Some(next) = self.find_next_task() {
             let mut inner = self.inner.exclusive_access();
             let current = inner.current_task;
+            let now = get_time_ms();
+            inner.record_dispatch(next, now);
             if inner.tasks[next].start_time == 0 {
-                inner.tasks[next].start_time = get_time_ms();
+                inner.tasks[next].start_time = now;
             }
             inner.tasks[next].task_status = TaskStatus::Running;
             inner.current_task = next;
@@ -166,7 +273,8 @@ impl TaskManager {
             }
             // go back to user mode
         } else {
-            panic!("All applications completed!");
+            println!("[kernel] All applications completed!");
+            shutdown();
         }
     }
 }
@@ -176,6 +284,17 @@ pub fn run_first_task() {
     TASK_MANAGER.run_first_task();
 }
 
+/// Get the id of the current `Running` task.
+pub fn current_task_id() -> usize {
+    TASK_MANAGER.get_inner().current_task
+}
+
+/// Get the id of the current task and the sequence number of its current syscall.
+pub fn current_trace_id() -> (usize, usize) {
+    let inner = TASK_MANAGER.get_inner();
+    (inner.current_task, inner.tasks[inner.current_task].syscall_seq)
+}
+
 /// Switch current `Running` task to the task we have found,
 /// or there is no `Ready` task and we can exit with all applications completed
 fn run_next_task() {
@@ -183,23 +302,45 @@ fn run_next_task() {
 }
 
 /// Change the status of current `Running` task into `Ready`.
-fn mark_current_suspended() {
-    TASK_MANAGER.mark_current_suspended();
+fn mark_current_suspended(preempted: bool) {
+    TASK_MANAGER.mark_current_suspended(preempted);
 }
 
 /// Change the status of current `Running` task into `Exited`.
-fn mark_current_exited() {
-    TASK_MANAGER.mark_current_exited();
+fn mark_current_exited(exit_code: i32) {
+    TASK_MANAGER.mark_current_exited(exit_code);
 }
 
 /// Suspend the current 'Running' task and run the next task in task list.
 pub fn suspend_current_and_run_next() {
-    mark_current_suspended();
+    mark_current_suspended(false);
     run_next_task();
 }
 
+/// Preempt the current 'Running' task and run the next task in task list.
+pub fn preempt_current_and_run_next() {
+    mark_current_suspended(true);
+    run_next_task();
+}
+
+/// Account a timer tick to the current task and update load estimations.
+pub fn on_timer_tick() {
+    TASK_MANAGER.on_timer_tick();
+}
+
+/// Kill the current 'Running' task for raising `fault` and run the next task in task list.
+pub fn kill_current_and_run_next(fault: UserFault, exit_code: i32) {
+    TASK_MANAGER.record_current_fault(fault);
+    exit_current_and_run_next(exit_code);
+}
+
+/// Get the scheduling statistics of the current task and of the scheduler.
+pub fn sched_stats() -> (TaskSchedStats, SchedStats) {
+    TASK_MANAGER.sched_stats()
+}
+
 /// Exit the current 'Running' task and run the next task in task list.
-pub fn exit_current_and_run_next() {
-    mark_current_exited();
+pub fn exit_current_and_run_next(exit_code: i32) {
+    mark_current_exited(exit_code);
     run_next_task();
 }
@@ -1,6 +1,8 @@
 //! Types related to task management
 
 use crate::config::MAX_SYSCALL_NUM;
+use crate::timer::IntervalTimer;
+use riscv::register::scause::Exception;
 use super::TaskContext;
 
 /// The task control block (TCB) of a task.
@@ -14,6 +16,48 @@ pub struct TaskControlBlock {
     pub syscall_times: [u32; MAX_SYSCALL_NUM],
     /// start time if the task
     pub start_time: usize,
+    /// sequence number of the syscall being handled, bumped at each dispatch
+    pub syscall_seq: usize,
+    /// scheduling statistics
+    pub sched_stats: TaskSchedStats,
+    /// periodic timer set by `sys_setitimer`
+    pub itimer: IntervalTimer,
+    /// exit code, valid once the task has exited
+    pub exit_code: i32,
+    /// the exception the task was killed by, if any
+    pub last_fault: Option<UserFault>,
+}
+
+/// An exception raised by a task in user mode
+#[derive(Copy, Clone)]
+pub struct UserFault {
+    /// the exception, from `scause`
+    pub cause: Exception,
+    /// address of the faulting instruction, from `sepc`
+    pub pc: usize,
+    /// the faulting address of a memory-access fault, from `stval`
+    pub addr: Option<usize>,
+    /// encoding of an illegal instruction, from `stval`, if the hart reports it
+    pub insn: Option<usize>,
+}
+
+/// Scheduling statistics of a task
+#[derive(Copy, Clone, Default)]
+pub struct TaskSchedStats {
+    /// times the task gave up the CPU by itself
+    pub voluntary_switches: usize,
+    /// times the task was preempted by a timer interrupt
+    pub involuntary_switches: usize,
+    /// times the task was picked to run
+    pub dispatches: usize,
+    /// total time in ms spent `Ready` but not running
+    pub ready_time: usize,
+    /// time in ms when the task last became `Ready`
+    pub ready_since: usize,
+    /// ticks the task was running in the current load period
+    pub period_ticks: usize,
+    /// recent CPU usage in fixed-point, decayed over about 5 seconds
+    pub recent_cpu: usize,
 }
 
 /// The status of a task
@@ -4,13 +4,15 @@ use crate::config::CLOCK_FREQ;
 use crate::sbi::set_timer;
 use riscv::register::time;
 /// The number of ticks per second
-const TICKS_PER_SEC: usize = 100;
+pub const TICKS_PER_SEC: usize = 100;
 #[allow(dead_code)]
 /// The number of milliseconds per second
 const MSEC_PER_SEC: usize = 1000;
 /// The number of microseconds per second
 #[allow(dead_code)]
 const MICRO_PER_SEC: usize = 1_000_000;
+/// The number of nanoseconds per second
+const NSEC_PER_SEC: usize = 1_000_000_000;
 
 /// Get the current time in ticks
 pub fn get_time() -> usize {
@@ -29,7 +31,49 @@ pub fn get_time_us() -> usize {
     time::read() * MICRO_PER_SEC / CLOCK_FREQ
 }
 
+/// get current time in nanoseconds
+///
+/// The product is computed in 128 bits, as it would overflow 64 bits within
+/// half an hour of uptime.
+pub fn get_time_ns() -> usize {
+    (time::read() as u128 * NSEC_PER_SEC as u128 / CLOCK_FREQ as u128) as usize
+}
+
+/// get the resolution of the clock in nanoseconds, rounded up
+pub fn get_time_res_ns() -> usize {
+    NSEC_PER_SEC.div_ceil(CLOCK_FREQ)
+}
+
 /// Set the next timer interrupt
 pub fn set_next_trigger() {
     set_timer(get_time() + CLOCK_FREQ / TICKS_PER_SEC);
 }
+
+/// A periodic timer counting its expirations
+#[derive(Copy, Clone, Default)]
+pub struct IntervalTimer {
+    /// period in milliseconds, 0 if the timer is disarmed
+    pub interval: usize,
+    /// time in milliseconds of the next expiration
+    pub next_expire: usize,
+    /// number of expirations since the timer was armed
+    pub expirations: usize,
+}
+
+impl IntervalTimer {
+    /// Count the expirations up to `now` (in milliseconds).
+    ///
+    /// Each deadline is computed from the previous one rather than from the
+    /// time it is noticed, so the timer doesn't drift. A deadline too far to
+    /// be represented saturates, so the timer never expires again.
+    pub fn update(&mut self, now: usize) {
+        if self.interval != 0 && now >= self.next_expire {
+            let expired = (now - self.next_expire) / self.interval + 1;
+            self.expirations = self.expirations.saturating_add(expired);
+            self.next_expire = expired
+                .checked_mul(self.interval)
+                .and_then(|delay| self.next_expire.checked_add(delay))
+                .unwrap_or(usize::MAX);
+        }
+    }
+}
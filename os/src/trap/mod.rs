@@ -14,16 +14,32 @@
 
 mod context;
 
+use crate::config::MAX_SYSCALL_NUM;
+use crate::loader::describe_user_addr;
 use crate::syscall::syscall;
-use crate::task::{exit_current_and_run_next, suspend_current_and_run_next, TASK_MANAGER};
+use crate::task::{
+    current_task_id, kill_current_and_run_next, on_timer_tick, preempt_current_and_run_next,
+    UserFault, TASK_MANAGER,
+};
 use crate::timer::set_next_trigger;
 use core::arch::global_asm;
 use riscv::register::{
     mtvec::TrapMode,
     scause::{self, Exception, Interrupt, Trap},
-    sie, stval, stvec,
+    sie,
+    sstatus::SPP,
+    stval, stvec,
 };
 
+/// exit code of a task killed for a bad or misaligned memory access
+const EXIT_SEGFAULT: i32 = -2;
+/// exit code of a task killed for an illegal instruction
+const EXIT_ILLEGAL_INSTRUCTION: i32 = -3;
+/// exit code of a task killed for any other exception, e.g. a breakpoint
+const EXIT_EXCEPTION: i32 = -4;
+/// `scause` of a misaligned load, which the `riscv` crate reports as `Unknown`
+const SCAUSE_LOAD_MISALIGNED: usize = 4;
+
 global_asm!(include_str!("trap.S"));
 
 /// Initialize trap handling
@@ -49,6 +65,14 @@ pub fn trap_handler(cx: &mut TrapContext) -> &mut TrapContext {
     let scause = scause::read(); // get trap cause
     let stval = stval::read(); // get extra value
                                // trace!("into {:?}", scause.cause());
+    if cx.sstatus.spp() == SPP::Supervisor {
+        panic!(
+            "Trap {:?} from kernel, stval = {:#x}, sepc = {:#x}!",
+            scause.cause(),
+            stval,
+            cx.sepc
+        );
+    }
     match scause.cause() {
         Trap::Exception(Exception::UserEnvCall) => {
             // jump to next instruction anyway
@@ -56,23 +80,23 @@ pub fn trap_handler(cx: &mut TrapContext) -> &mut TrapContext {
             let syscall_id = cx.x[17];
             let mut inner = TASK_MANAGER.get_inner();
             let mut tcb = inner.get_current_task();
-            tcb.syscall_times[syscall_id] += 1;
+            if syscall_id < MAX_SYSCALL_NUM {
+                tcb.syscall_times[syscall_id] += 1;
+            }
+            tcb.syscall_seq += 1;
             inner.set_current_task(tcb);
             drop(inner);
             // get system call return value
-            cx.x[10] = syscall(syscall_id, [cx.x[10], cx.x[11], cx.x[12]]) as usize;
-        }
-        Trap::Exception(Exception::StoreFault) | Trap::Exception(Exception::StorePageFault) => {
-            println!("[kernel] PageFault in application, bad addr = {:#x}, bad instruction = {:#x}, kernel killed it.", stval, cx.sepc);
-            exit_current_and_run_next();
+            cx.x[10] = syscall(syscall_id, [cx.x[10], cx.x[11], cx.x[12], cx.x[13]]) as usize;
         }
-        Trap::Exception(Exception::IllegalInstruction) => {
-            println!("[kernel] IllegalInstruction in application, kernel killed it.");
-            exit_current_and_run_next();
+        Trap::Exception(cause) => {
+            let (fault, exit_code) = classify_fault(cause, scause.bits(), stval, cx.sepc);
+            kill_current(fault, exit_code);
         }
         Trap::Interrupt(Interrupt::SupervisorTimer) => {
             set_next_trigger();
-            suspend_current_and_run_next();
+            on_timer_tick();
+            preempt_current_and_run_next();
         }
         _ => {
             panic!(
@@ -85,4 +109,59 @@ pub fn trap_handler(cx: &mut TrapContext) -> &mut TrapContext {
     cx
 }
 
+/// Build the fault record of exception `cause` raised at `pc` from user mode,
+/// and pick the exit code of the task killed for it.
+fn classify_fault(cause: Exception, scause: usize, stval: usize, pc: usize) -> (UserFault, i32) {
+    let mut fault = UserFault {
+        cause,
+        pc,
+        addr: None,
+        insn: None,
+    };
+    match cause {
+        Exception::InstructionMisaligned
+        | Exception::InstructionFault
+        | Exception::InstructionPageFault
+        | Exception::LoadFault
+        | Exception::LoadPageFault
+        | Exception::StoreMisaligned
+        | Exception::StoreFault
+        | Exception::StorePageFault => {
+            fault.addr = Some(stval);
+            (fault, EXIT_SEGFAULT)
+        }
+        Exception::Unknown if scause == SCAUSE_LOAD_MISALIGNED => {
+            fault.addr = Some(stval);
+            (fault, EXIT_SEGFAULT)
+        }
+        Exception::IllegalInstruction => {
+            // stval is 0 if the hart doesn't report the instruction
+            fault.insn = if stval != 0 { Some(stval) } else { None };
+            (fault, EXIT_ILLEGAL_INSTRUCTION)
+        }
+        _ => (fault, EXIT_EXCEPTION),
+    }
+}
+
+/// Kill the current task for raising `fault` and run the next task,
+/// printing where the faulting address, or else the faulting instruction, lies.
+fn kill_current(fault: UserFault, exit_code: i32) {
+    let task_id = current_task_id();
+    print!("[kernel] task {} killed: {:?}", task_id, fault.cause);
+    if let Some(insn) = fault.insn {
+        print!(" {:#010x}", insn);
+    }
+    print!(" at pc = {:#x}", fault.pc);
+    match fault.addr {
+        Some(addr) => print!(
+            ", addr = {:#x} ({})",
+            addr,
+            describe_user_addr(task_id, addr)
+        ),
+        None => print!(" ({})", describe_user_addr(task_id, fault.pc)),
+    }
+    println!(", exit code {}", exit_code);
+    kill_current_and_run_next(fault, exit_code);
+}
+
 pub use context::TrapContext;